@@ -8,7 +8,7 @@
 //! NSM Rust API.
 
 use aws_nitro_enclaves_nsm_api::api::{Digest, Request, Response};
-use aws_nitro_enclaves_nsm_api::driver::{nsm_exit, nsm_init, nsm_process_request};
+use aws_nitro_enclaves_nsm_api::driver::Nsm;
 use std::collections::BTreeSet;
 
 const RESERVED_PCRS: u16 = 5;
@@ -30,11 +30,11 @@ struct NsmDescription {
     digest: Digest,
 }
 
-/// Get the description of the NSM.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.  
+/// Get the description of the NSM.
+/// *Argument 1 (input)*: An open NSM device handle.
 /// *Returns*: A description structure.
-fn get_nsm_description(ctx: i32) -> NsmDescription {
-    let response = nsm_process_request(ctx, Request::DescribeNSM);
+fn get_nsm_description(nsm: &Nsm) -> NsmDescription {
+    let response = nsm.process_request(Request::DescribeNSM);
     match response {
         Response::DescribeNSM {
             version_major,
@@ -71,16 +71,16 @@ fn get_pcr_len(description: &NsmDescription) -> usize {
     }
 }
 
-/// Test the initial state of the PCRs.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.  
+/// Test the initial state of the PCRs.
+/// *Argument 1 (input)*: An open NSM device handle.
 /// *Argument 2 (input)*: The NSM description.
-fn check_initial_pcrs(ctx: i32, description: &NsmDescription) {
+fn check_initial_pcrs(nsm: &Nsm, description: &NsmDescription) {
     let expected_pcr_len = get_pcr_len(description);
 
     // First, get the description of all available PCRs.
     let pcr_data: Vec<PcrData> = (0..description.max_pcrs)
         .map(|pcr| {
-            let response = nsm_process_request(ctx, Request::DescribePCR { index: pcr as u16 });
+            let response = nsm.process_request(Request::DescribePCR { index: pcr as u16 });
             match response {
                 Response::DescribePCR { lock, data } => {
                     assert_eq!(
@@ -161,10 +161,10 @@ fn check_initial_pcrs(ctx: i32, description: &NsmDescription) {
     );
 }
 
-/// Check and modify the lock state of the PCRs.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.  
+/// Check and modify the lock state of the PCRs.
+/// *Argument 1 (input)*: An open NSM device handle.
 /// *Argument 2 (input)*: The NSM description.
-fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
+fn check_pcr_locks(nsm: &Nsm, description: &NsmDescription) {
     let dummy_data: Vec<u8> = vec![1, 2, 3];
     let expected_pcr_len = get_pcr_len(description);
     let zeroed_pcr: Vec<u8> = vec![0; expected_pcr_len];
@@ -173,7 +173,7 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
 
     // Test that PCRs [0..16) cannot be locked.
     for index in 0..16 {
-        response = nsm_process_request(ctx, Request::LockPCR { index });
+        response = nsm.process_request(Request::LockPCR { index });
         match response {
             Response::Error(_) => (),
             _ => panic!(
@@ -189,13 +189,10 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
     for loop_idx in 0..10 {
         for index in 16..description.max_pcrs {
             let data_copy = dummy_data.clone();
-            response = nsm_process_request(
-                ctx,
-                Request::ExtendPCR {
-                    index,
-                    data: data_copy,
-                },
-            );
+            response = nsm.process_request(Request::ExtendPCR {
+                index,
+                data: data_copy,
+            });
 
             match response {
                 Response::ExtendPCR { data } => {
@@ -221,7 +218,7 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
 
     // Lock all remaining PCRs.
     for index in 16..description.max_pcrs {
-        response = nsm_process_request(ctx, Request::LockPCR { index });
+        response = nsm.process_request(Request::LockPCR { index });
 
         match response {
             Response::LockPCR => (),
@@ -238,7 +235,7 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
     );
 
     // Lock PCRs in a valid range.
-    response = nsm_process_request(ctx, Request::LockPCRs { range });
+    response = nsm.process_request(Request::LockPCRs { range });
     match response {
         Response::LockPCRs => (),
         _ => panic!(
@@ -249,7 +246,7 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
 
     // Lock PCRs in an invalid range.
     range += 1;
-    response = nsm_process_request(ctx, Request::LockPCRs { range });
+    response = nsm.process_request(Request::LockPCRs { range });
     match response {
         Response::Error(_) => (),
         _ => panic!(
@@ -267,13 +264,10 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
     // Attempt to extend locked PCRs.
     for index in 0..description.max_pcrs {
         let data_copy = dummy_data.clone();
-        response = nsm_process_request(
-            ctx,
-            Request::ExtendPCR {
-                index,
-                data: data_copy,
-            },
-        );
+        response = nsm.process_request(Request::ExtendPCR {
+            index,
+            data: data_copy,
+        });
 
         match response {
             Response::Error(_) => (),
@@ -292,7 +286,7 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
     // Describe all PCRs multiple times.
     for loop_idx in 0..10 {
         for index in 0..description.max_pcrs {
-            response = nsm_process_request(ctx, Request::DescribePCR { index });
+            response = nsm.process_request(Request::DescribePCR { index });
 
             match response {
                 Response::DescribePCR { lock, data } => {
@@ -329,25 +323,22 @@ fn check_pcr_locks(ctx: i32, description: &NsmDescription) {
     }
 }
 
-/// Check a single attestation operation.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.  
-/// *Argument 2 (input)*: Optional user data.  
-/// *Argument 3 (input)*: Optional nonce data.  
+/// Check a single attestation operation.
+/// *Argument 1 (input)*: An open NSM device handle.
+/// *Argument 2 (input)*: Optional user data.
+/// *Argument 3 (input)*: Optional nonce data.
 /// *Argument 4 (input)*: Optional public key.
 fn check_single_attestation(
-    ctx: i32,
+    nsm: &Nsm,
     user_data: Option<Vec<u8>>,
     nonce: Option<Vec<u8>>,
     public_key: Option<Vec<u8>>,
 ) {
-    let response = nsm_process_request(
-        ctx,
-        Request::Attestation {
-            user_data,
-            nonce,
-            public_key,
-        },
-    );
+    let response = nsm.process_request(Request::Attestation {
+        user_data,
+        nonce,
+        public_key,
+    });
     match response {
         Response::Attestation { document } => {
             assert_ne!(document.len(), 0, "[Error] Attestation document is empty.");
@@ -359,23 +350,23 @@ fn check_single_attestation(
     }
 }
 
-/// Check multiple attestation operations.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.
-fn check_attestation(ctx: i32) {
+/// Check multiple attestation operations.
+/// *Argument 1 (input)*: An open NSM device handle.
+fn check_attestation(nsm: &Nsm) {
     const DATA_LEN: usize = 1024;
     let dummy_data: Vec<u8> = vec![128; DATA_LEN];
 
-    check_single_attestation(ctx, None, None, None);
+    check_single_attestation(nsm, None, None, None);
     println!("Checked Request::Attestation without any data.");
 
-    check_single_attestation(ctx, Some(dummy_data.clone())), None, None);
+    check_single_attestation(nsm, Some(dummy_data.clone()), None, None);
     println!(
         "Checked Request::Attestation with user data ({} bytes).",
         DATA_LEN
     );
 
     check_single_attestation(
-        ctx,
+        nsm,
         Some(dummy_data.clone()),
         Some(dummy_data.clone()),
         None,
@@ -386,7 +377,7 @@ fn check_attestation(ctx: i32) {
     );
 
     check_single_attestation(
-        ctx,
+        nsm,
         Some(dummy_data.clone()),
         Some(dummy_data.clone()),
         Some(dummy_data.clone()),
@@ -397,11 +388,11 @@ fn check_attestation(ctx: i32) {
     );
 }
 
-fn check_random(ctx: i32) {
+fn check_random(nsm: &Nsm) {
     let mut prev_random: Vec<u8> = vec![];
 
     for _ in 0..16 {
-        match nsm_process_request(ctx, Request::GetRandom) {
+        match nsm.process_request(Request::GetRandom) {
             Response::GetRandom { random } => {
                 assert!(!random.is_empty());
                 assert!(prev_random != random);
@@ -419,10 +410,9 @@ fn check_random(ctx: i32) {
 fn main() {
     println!("NSM test started.");
 
-    let ctx = nsm_init();
-    assert!(ctx >= 0, "[Error] NSM initialization returned {}.", ctx);
+    let nsm = Nsm::open().expect("[Error] NSM initialization failed.");
 
-    let description = get_nsm_description(ctx);
+    let description = get_nsm_description(&nsm);
     assert_eq!(
         description.max_pcrs, 32,
         "[Error] NSM PCR count is {}.",
@@ -445,16 +435,16 @@ fn main() {
         description.digest
     );
 
-    check_single_attestation(ctx, None, None, None);
+    check_single_attestation(&nsm, None, None, None);
     println!("Checked Request::Attestation without any data.");
 
-    check_initial_pcrs(ctx, &description);
-    check_pcr_locks(ctx, &description);
+    check_initial_pcrs(&nsm, &description);
+    check_pcr_locks(&nsm, &description);
 
-    check_attestation(ctx);
+    check_attestation(&nsm);
 
-    check_random(ctx);
+    check_random(&nsm);
 
-    nsm_exit(ctx);
+    // `nsm`'s descriptor is closed automatically when it's dropped here.
     println!("NSM test finished.");
 }
@@ -8,8 +8,8 @@
 //! This module implements a aggresive run-time test for the
 //! NSM Rust API.
 
-use aws_nitro_enclaves_nsm_api::api::{Request, Response};
-use aws_nitro_enclaves_nsm_api::driver::{nsm_exit, nsm_init, nsm_process_request};
+use aws_nitro_enclaves_nsm_api::api::{Digest, Request, Response};
+use aws_nitro_enclaves_nsm_api::driver::Nsm;
 use serde_bytes::ByteBuf;
 use std::convert::TryInto;
 use std::sync::atomic;
@@ -21,51 +21,61 @@ use threadpool::ThreadPool;
 enum ErrorCode {
     AttestationDocumentEmpty = 1,
     AttestationInvalidResponse = 2,
+    ExtendPcrInvalidResponse = 3,
 }
 
-/// *Argument 2 (input)*: The NSM description.
-fn extend_pcr(ctx: i32, j: usize) {
+/// Extend PCR `(16 + j) & 15` twice, asserting that each `Response::ExtendPCR` has
+/// the length expected for `digest` (the extend recurrence's exact value is not
+/// predictable here since many threads race to extend the same PCRs).
+/// *Argument 2 (input)*: The NSM description's digest algorithm.
+fn extend_pcr(nsm: &Nsm, digest: Digest, j: usize) -> Result<(), ErrorCode> {
     let pcr: u16 = ((16 + j) & 15).try_into().unwrap();
     let one: u8 = ((j >> 24) & 0xFF).try_into().unwrap();
     let two: u8 = ((j >> 16) & 0xFF).try_into().unwrap();
     let three: u8 = ((j >> 8) & 0xFF).try_into().unwrap();
     let four: u8 = (j & 0xFF).try_into().unwrap();
     let dummy_data: Vec<u8> = vec![one, two, three, four];
-    let mut _response: Response;
 
     // Extend the remaining PCRs multiple times.
     for _loop_idx in 0..2 {
         let data_copy = dummy_data.clone();
-        _response = nsm_process_request(
-            ctx,
-            Request::ExtendPCR {
-                index: pcr,
-                data: data_copy,
-            },
-        );
+        let response = nsm.process_request(Request::ExtendPCR {
+            index: pcr,
+            data: data_copy,
+        });
+
+        match response {
+            Response::ExtendPCR { data } if data.len() == digest.hash_len() => (),
+            _ => {
+                println!(
+                    "[Error] Request::ExtendPCR got invalid response: {:?}",
+                    response
+                );
+                return Err(ErrorCode::ExtendPcrInvalidResponse);
+            }
+        }
     }
+
+    Ok(())
 }
 
-/// Check a single attestation operation.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.  
-/// *Argument 2 (input)*: Optional user data.  
-/// *Argument 3 (input)*: Optional nonce data.  
+/// Check a single attestation operation.
+/// *Argument 1 (input)*: An open NSM device handle.
+/// *Argument 2 (input)*: Optional user data.
+/// *Argument 3 (input)*: Optional nonce data.
 /// *Argument 4 (input)*: Optional public key.
 /// Returns Ok(()) in case of success
 fn check_single_attestation(
-    ctx: i32,
+    nsm: &Nsm,
     user_data: Option<ByteBuf>,
     nonce: Option<ByteBuf>,
     public_key: Option<ByteBuf>,
 ) -> Result<(), ErrorCode> {
-    let response = nsm_process_request(
-        ctx,
-        Request::Attestation {
-            user_data,
-            nonce,
-            public_key,
-        },
-    );
+    let response = nsm.process_request(Request::Attestation {
+        user_data,
+        nonce,
+        public_key,
+    });
     match response {
         Response::Attestation { document } => {
             if document.is_empty() {
@@ -84,15 +94,15 @@ fn check_single_attestation(
     Ok(())
 }
 
-/// Check multiple attestation operations.  
-/// *Argument 1 (input)*: Context from `nsm_init()`.
+/// Check multiple attestation operations.
+/// *Argument 1 (input)*: An open NSM device handle.
 /// Returns Ok(()) in case of success
-fn check_attestation(ctx: i32, lp: usize) -> Result<(), ErrorCode> {
+fn check_attestation(nsm: &Nsm, lp: usize) -> Result<(), ErrorCode> {
     const DATA_LEN: usize = 1024;
     let dummy_data: Vec<u8> = vec![128; DATA_LEN];
     let mut now = time::Instant::now();
 
-    check_single_attestation(ctx, None, None, None)?;
+    check_single_attestation(nsm, None, None, None)?;
     println!(
         "attestation loop={} wo/data took {} ns",
         lp,
@@ -100,7 +110,7 @@ fn check_attestation(ctx: i32, lp: usize) -> Result<(), ErrorCode> {
     );
     now = time::Instant::now();
 
-    check_single_attestation(ctx, Some(ByteBuf::from(&dummy_data[..])), None, None)?;
+    check_single_attestation(nsm, Some(ByteBuf::from(&dummy_data[..])), None, None)?;
     println!(
         "attestation loop={} w/data took {} ns",
         lp,
@@ -109,7 +119,7 @@ fn check_attestation(ctx: i32, lp: usize) -> Result<(), ErrorCode> {
     now = time::Instant::now();
 
     check_single_attestation(
-        ctx,
+        nsm,
         Some(ByteBuf::from(&dummy_data[..])),
         Some(ByteBuf::from(&dummy_data[..])),
         None,
@@ -122,7 +132,7 @@ fn check_attestation(ctx: i32, lp: usize) -> Result<(), ErrorCode> {
     now = time::Instant::now();
 
     check_single_attestation(
-        ctx,
+        nsm,
         Some(ByteBuf::from(&dummy_data[..])),
         Some(ByteBuf::from(&dummy_data[..])),
         Some(ByteBuf::from(&dummy_data[..])),
@@ -143,8 +153,15 @@ fn main() {
     signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&term))
         .expect("Failed to register signal hook");
 
-    let ctx = nsm_init();
-    assert!(ctx >= 0, "[Error] NSM initialization returned {}.", ctx);
+    let nsm = Arc::new(Nsm::open().expect("[Error] NSM initialization failed."));
+
+    let digest = match nsm.process_request(Request::DescribeNSM) {
+        Response::DescribeNSM { digest, .. } => digest,
+        response => panic!(
+            "[Error] Request::DescribeNSM got invalid response: {:?}",
+            response
+        ),
+    };
 
     // 90 threads is the limit for ~200M of memory
     let index = 90;
@@ -165,8 +182,9 @@ fn main() {
             thread::sleep(time::Duration::from_millis(100));
         }
         let exit_code_t = Arc::clone(&exit_code);
+        let nsm_t = Arc::clone(&nsm);
         pool.execute(move || {
-            let exit_code = check_attestation(ctx, j);
+            let exit_code = check_attestation(&nsm_t, j);
             if let Err(e) = exit_code {
                 if let Err(er) = exit_code_t.compare_exchange(
                     0,
@@ -179,14 +197,25 @@ fn main() {
             }
         });
         j += 1;
+        let exit_code_t = Arc::clone(&exit_code);
+        let nsm_t = Arc::clone(&nsm);
         pool.execute(move || {
-            extend_pcr(ctx, j);
+            if let Err(e) = extend_pcr(&nsm_t, digest, j) {
+                if let Err(er) = exit_code_t.compare_exchange(
+                    0,
+                    e as i32,
+                    atomic::Ordering::Relaxed,
+                    atomic::Ordering::Relaxed,
+                ) {
+                    println!("{:?}", er);
+                }
+            }
         });
         j += 1;
     } //while
 
     pool.join();
-    nsm_exit(ctx);
+    // `nsm`'s descriptor is closed automatically when the last `Arc` is dropped.
 
     println!(
         "NSM test finished. Exitcode: {}",
@@ -0,0 +1,71 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Pluggable CBOR (de)serialization backend for `Request`/`Response`.
+//! # Overview
+//! The NSM wire format is just CBOR, but different consumers of the `api`
+//! types want different CBOR stacks: `no_std` users of the `api` types need an
+//! encoder that builds without `std`, while other consumers may already depend on a serde-based
+//! CBOR crate. `CborCodec` lets the encoding/decoding implementation be chosen
+//! at compile time via a cargo feature, without changing the `Request`/
+//! `Response` types themselves.
+
+#[cfg(feature = "std")]
+use std::{format, string::String, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+use crate::api::{Request, Response};
+
+/// A CBOR (de)serialization backend for the NSM wire format.
+pub trait CborCodec {
+    /// Encode a `Request` into its CBOR representation.
+    fn encode_request(request: &Request) -> Vec<u8>;
+
+    /// Decode a `Response` from its CBOR representation.
+    fn decode_response(bytes: &[u8]) -> Result<Response, String>;
+}
+
+/// The default backend, based on the `minicbor` crate. This is the backend used
+/// by the `driver` module unless both the `ciborium` and `serde` features are
+/// enabled, and it compiles under `no_std` + `alloc`.
+pub struct MinicborCodec;
+
+impl CborCodec for MinicborCodec {
+    fn encode_request(request: &Request) -> Vec<u8> {
+        // `minicbor::to_vec` is infallible: https://gitlab.com/twittner/minicbor/-/blob/develop/minicbor/src/lib.rs#L196
+        minicbor::to_vec(request).expect("`minicbor::to_vec` is infallible")
+    }
+
+    fn decode_response(bytes: &[u8]) -> Result<Response, String> {
+        minicbor::decode(bytes).map_err(|e| format!("{}", e))
+    }
+}
+
+/// An alternative backend based on the `ciborium` crate's serde support.
+/// Requires both the `ciborium` and `serde` cargo features, since it relies on
+/// the `Serialize`/`Deserialize` derives gated behind the `serde` feature.
+#[cfg(all(feature = "ciborium", feature = "serde"))]
+pub struct CiboriumCodec;
+
+#[cfg(all(feature = "ciborium", feature = "serde"))]
+impl CborCodec for CiboriumCodec {
+    fn encode_request(request: &Request) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(request, &mut bytes).expect("ciborium encoding is infallible");
+        bytes
+    }
+
+    fn decode_response(bytes: &[u8]) -> Result<Response, String> {
+        ciborium::de::from_reader(bytes).map_err(|e| format!("{}", e))
+    }
+}
+
+/// The codec selected at compile time for the `driver` module's wire format.
+#[cfg(not(all(feature = "ciborium", feature = "serde")))]
+pub type ActiveCodec = MinicborCodec;
+
+/// The codec selected at compile time for the `driver` module's wire format.
+#[cfg(all(feature = "ciborium", feature = "serde"))]
+pub type ActiveCodec = CiboriumCodec;
@@ -0,0 +1,165 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A typed COSE_Sign1 codec.
+//! # Overview
+//! `Response::Attestation.document` is an untyped `Vec<u8>` holding a
+//! COSE_Sign1 value: the CBOR array `[protected, unprotected, payload,
+//! signature]`. Rather than have every producer and verifier hand-roll that
+//! framing, `CoseSign1` gives it a typed, `minicbor`-derived representation
+//! that can be encoded, decoded, and turned back into the canonical
+//! `Sig_structure` used for signing and verification.
+
+use minicbor::{Decode, Encode};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// The COSE algorithm identifier for ECDSA using the P-384 curve and SHA-384,
+/// as used by the NitroSecureModule to sign attestation documents.
+pub const ALG_ES384: i64 = -35;
+
+/// A COSE_Sign1 value: a signed, single-signer CBOR structure consisting of a
+/// protected header, an unprotected header, a payload, and a signature.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cbor(array)]
+pub struct CoseSign1 {
+    /// The protected header, CBOR-encoded as a byte string so it is covered by
+    /// the signature verbatim. Typically a one-entry map `{1: alg}`.
+    #[n(0)]
+    #[cbor(with = "minicbor::bytes")]
+    pub protected: Vec<u8>,
+
+    /// The unprotected header, a plain CBOR map not covered by the signature.
+    #[n(1)]
+    pub unprotected: BTreeMap<i64, Vec<u8>>,
+
+    /// The signed payload (for attestation, a CBOR-encoded `AttestationDoc`).
+    #[n(2)]
+    #[cbor(with = "minicbor::bytes")]
+    pub payload: Vec<u8>,
+
+    /// The raw signature bytes (for ES384, 96 bytes: `r || s`).
+    #[n(3)]
+    #[cbor(with = "minicbor::bytes")]
+    pub signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Decode a `CoseSign1` from its CBOR array representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, minicbor::decode::Error> {
+        minicbor::decode(bytes)
+    }
+
+    /// CBOR-encode this value back into its COSE_Sign1 array representation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        minicbor::to_vec(self).expect("`minicbor::to_vec` is infallible")
+    }
+
+    /// The signed payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The COSE algorithm identifier declared in the protected header (e.g.
+    /// [`ALG_ES384`]), if the header is a well-formed `{1: alg}` CBOR map.
+    pub fn algorithm(&self) -> Option<i64> {
+        let mut decoder = minicbor::decode::Decoder::new(&self.protected);
+        let len = decoder.map().ok()??;
+        for _ in 0..len {
+            let key: i64 = decoder.decode().ok()?;
+            if key == 1 {
+                return decoder.decode().ok();
+            }
+            decoder.skip().ok()?;
+        }
+        None
+    }
+
+    /// Build the canonical COSE `Sig_structure` that is actually signed: the
+    /// CBOR array `["Signature1", protected, external_aad, payload]`, with
+    /// `external_aad` empty, as used by both the signer and the verifier.
+    pub fn sig_structure(&self) -> Vec<u8> {
+        let mut encoder = minicbor::encode::Encoder::new(Vec::new());
+        encoder
+            .array(4)
+            .and_then(|e| e.str("Signature1"))
+            .and_then(|e| e.bytes(&self.protected))
+            .and_then(|e| e.bytes(&[]))
+            .and_then(|e| e.bytes(&self.payload))
+            .expect("encoding a Sig_structure of known shape is infallible");
+        encoder.into_writer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protected_header(alg: i64) -> Vec<u8> {
+        let mut encoder = minicbor::encode::Encoder::new(Vec::new());
+        encoder
+            .map(1)
+            .and_then(|e| e.i64(1))
+            .and_then(|e| e.i64(alg))
+            .expect("encoding a one-entry protected header is infallible");
+        encoder.into_writer()
+    }
+
+    #[test]
+    fn test_cose_sign1_binary_roundtrip() {
+        let cose = CoseSign1 {
+            protected: protected_header(ALG_ES384),
+            unprotected: BTreeMap::new(),
+            payload: vec![1, 2, 3],
+            signature: vec![4; 96],
+        };
+
+        let bytes = cose.to_bytes();
+        let decoded = CoseSign1::from_bytes(&bytes).unwrap();
+
+        assert_eq!(cose, decoded);
+        assert_eq!(cose.payload(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_cose_sign1_algorithm() {
+        let cose = CoseSign1 {
+            protected: protected_header(ALG_ES384),
+            unprotected: BTreeMap::new(),
+            payload: vec![],
+            signature: vec![],
+        };
+        assert_eq!(cose.algorithm(), Some(ALG_ES384));
+
+        let no_alg = CoseSign1 {
+            protected: vec![],
+            unprotected: BTreeMap::new(),
+            payload: vec![],
+            signature: vec![],
+        };
+        assert_eq!(no_alg.algorithm(), None);
+    }
+
+    #[test]
+    fn test_cose_sign1_sig_structure_is_deterministic() {
+        let cose = CoseSign1 {
+            protected: protected_header(ALG_ES384),
+            unprotected: BTreeMap::new(),
+            payload: vec![9, 9, 9],
+            signature: vec![],
+        };
+
+        let sig_structure = cose.sig_structure();
+        assert_eq!(sig_structure, cose.sig_structure());
+        // Sig_structure = ["Signature1", protected, external_aad (empty), payload]
+        assert!(sig_structure.ends_with(&[0x43, 9, 9, 9]));
+    }
+}
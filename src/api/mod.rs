@@ -11,14 +11,33 @@
 
 // BTreeMap preserves ordering, which makes the tests easier to write
 use minicbor::{Decode, Encode};
+#[cfg(feature = "std")]
 use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "std")]
 use std::io::Error as IoError;
+#[cfg(feature = "std")]
 use std::result;
 
+#[cfg(not(feature = "std"))]
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(not(feature = "std"))]
+use core::result;
+
+pub mod codec;
+pub mod cose;
+pub mod policy;
+pub mod revocation;
+
+#[cfg(feature = "std")]
+pub mod verify;
+
 #[derive(Debug)]
 /// Possible error types return from this library.
 pub enum Error {
     /// An IO error of type `std::io::Error`
+    #[cfg(feature = "std")]
     Io(IoError),
     /// An error attempting to decode with the `minicbor` library.
     CborDecode(minicbor::decode::Error),
@@ -27,6 +46,7 @@ pub enum Error {
 /// Result type return nsm-io::Error on failure.
 pub type Result<T> = result::Result<T, Error>;
 
+#[cfg(feature = "std")]
 impl From<IoError> for Error {
     fn from(error: IoError) -> Self {
         Error::Io(error)
@@ -42,6 +62,7 @@ impl From<minicbor::decode::Error> for Error {
 /// List of error codes that the NSM module can return as part of a Response
 #[repr(C)]
 #[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ErrorCode {
     /// No errors
      #[n(0)] Success,
@@ -74,6 +95,7 @@ pub enum ErrorCode {
 
 /// Operations that a NitroSecureModule should implement. Assumes 64K registers will be enough for everyone.
 #[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum Request {
     /// Read data from PlatformConfigurationRegister at `index`
@@ -88,6 +110,7 @@ pub enum Request {
         #[n(0)] index: u16,
 
         /// data to extend it with
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(1)] data: Vec<u8>,
     },
 
@@ -112,12 +135,15 @@ pub enum Request {
     /// authenticity.
     #[n(5)] Attestation {
         /// Includes additional user data in the AttestationDoc.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(0)] user_data: Option<Vec<u8>>,
 
         /// Includes an additional nonce in the AttestationDoc.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(1)] nonce: Option<Vec<u8>>,
 
         /// Includes a user provided public key in the AttestationDoc.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(2)] public_key: Option<Vec<u8>>,
     },
 
@@ -127,6 +153,7 @@ pub enum Request {
 
 /// Responses received from a NitroSecureModule as a result of a Request
 #[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum Response {
     /// returns the current PlatformConfigurationRegister state
@@ -134,12 +161,14 @@ pub enum Response {
         /// true if the PCR is read-only, false otherwise
         #[n(0)] lock: bool,
         /// the current value of the PCR
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(1)] data: Vec<u8>,
     },
 
     /// returned if PlatformConfigurationRegister has been successfully extended
     #[n(1)] ExtendPCR {
         /// The new value of the PCR after extending the data into the register.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(0)] data: Vec<u8>,
     },
 
@@ -171,12 +200,14 @@ pub enum Response {
     /// signature generated from the doc by the NitroSecureModule
     #[n(5)] Attestation {
         /// A signed COSE structure containing a CBOR-encoded AttestationDocument as the payload.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
        #[n(0)] document: Vec<u8>,
     },
 
     /// A response containing a number of bytes of entropy.
     #[n(6)] GetRandom {
         /// The random bytes.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         #[n(0)] random: Vec<u8>,
     },
 
@@ -187,6 +218,7 @@ pub enum Response {
 /// The digest implementation used by a NitroSecureModule
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Digest {
     /// SHA256
     #[n(0)] SHA256,
@@ -196,6 +228,39 @@ pub enum Digest {
     #[n(2)] SHA512,
 }
 
+impl Digest {
+    /// The output length, in bytes, of this digest algorithm, and therefore of any
+    /// PCR value computed with it.
+    pub fn hash_len(&self) -> usize {
+        match self {
+            Digest::SHA256 => 32,
+            Digest::SHA384 => 48,
+            Digest::SHA512 => 64,
+        }
+    }
+}
+
+/// Compute the new value of a PlatformConfigurationRegister after extending it with
+/// `data`, the same way the NSM does: `new = H(current || H(data))`, with `current`
+/// expected to be `digest.hash_len()` bytes (the initial register state is that many
+/// zero bytes). This lets a verifier independently recompute the expected `pcrs` map
+/// in an `AttestationDoc` from a known boot sequence, without a round-trip to the NSM.
+pub fn extend_pcr(digest: Digest, current: &[u8], data: &[u8]) -> Vec<u8> {
+    fn hash_extend<D: sha2::Digest>(current: &[u8], data: &[u8]) -> Vec<u8> {
+        let data_digest = D::digest(data);
+        let mut hasher = D::new();
+        hasher.update(current);
+        hasher.update(data_digest);
+        hasher.finalize().to_vec()
+    }
+
+    match digest {
+        Digest::SHA256 => hash_extend::<sha2::Sha256>(current, data),
+        Digest::SHA384 => hash_extend::<sha2::Sha384>(current, data),
+        Digest::SHA512 => hash_extend::<sha2::Sha512>(current, data),
+    }
+}
+
 /// An attestation response.  This is also used for sealing
 /// data.
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
@@ -230,6 +295,50 @@ pub struct AttestationDoc {
     #[n(8)] pub nonce: Option<Vec<u8>>,
 }
 
+/// The point of implementing `serde` for `AttestationDoc` is so a document
+/// produced by `to_binary()` (the `minicbor` wire format relying parties
+/// actually receive) can be read back with a generic `serde`-based CBOR
+/// reader such as `ciborium::de::from_reader`. A derived `Serialize`/
+/// `Deserialize` can't deliver that: `minicbor` encodes this struct as a CBOR
+/// map keyed by the small integers in its `#[n(k)]` attributes, while a
+/// derived `serde` impl encodes it as a map keyed by field name strings - a
+/// different, incompatible wire format. Instead, route through
+/// `ciborium::value::Value`, a generic in-memory CBOR value: this makes the
+/// `serde` encoding byte-for-byte whatever `minicbor` produces, by
+/// construction, rather than an independently-derived approximation of it.
+/// This relies directly on the `ciborium` crate rather than `serde` alone, so
+/// it's gated the same way `codec::CiboriumCodec` is.
+#[cfg(all(feature = "ciborium", feature = "serde"))]
+impl serde::Serialize for AttestationDoc {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let value: ciborium::value::Value =
+            ciborium::de::from_reader(&self.to_binary()[..]).map_err(|e| {
+                serde::ser::Error::custom(format!("minicbor output is not valid CBOR: {:?}", e))
+            })?;
+        <ciborium::value::Value as serde::Serialize>::serialize(&value, serializer)
+    }
+}
+
+#[cfg(all(feature = "ciborium", feature = "serde"))]
+impl<'de> serde::Deserialize<'de> for AttestationDoc {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value =
+            <ciborium::value::Value as serde::Deserialize>::deserialize(deserializer)?;
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&value, &mut bytes).map_err(|e| {
+            serde::de::Error::custom(format!("failed to re-encode CBOR value: {:?}", e))
+        })?;
+        AttestationDoc::from_binary(&bytes)
+            .map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+    }
+}
+
 impl AttestationDoc {
     /// Creates a new AttestationDoc.
     ///
@@ -288,6 +397,22 @@ impl AttestationDoc {
 mod tests {
     use super::*;
 
+    /// Known-answer test for `extend_pcr(SHA256, ..)`, independent of its own
+    /// implementation, so a transposition in the `H(current || H(data))` order
+    /// or an off-by-one in the initial (all-zero) register state would be
+    /// caught instead of only showing up as a mismatch against a real NSM.
+    #[test]
+    fn test_extend_pcr_sha256_known_answer() {
+        let current = vec![0u8; Digest::SHA256.hash_len()];
+        let data = b"hello".to_vec();
+        let expected: Vec<u8> = vec![
+            0x98, 0x51, 0x31, 0x20, 0x28, 0x95, 0x25, 0x21, 0x51, 0x0e, 0x8e, 0xaa, 0xb5, 0xbe,
+            0x94, 0xe7, 0xdc, 0x24, 0xb5, 0xfc, 0x29, 0x2b, 0x2e, 0x97, 0x81, 0x17, 0x3c, 0xf1,
+            0x1f, 0xfa, 0x98, 0x78,
+        ];
+        assert_eq!(extend_pcr(Digest::SHA256, &current, &data), expected);
+    }
+
     #[test]
     fn test_attestationdoc_binary_encode() {
         let mut pcrs = BTreeMap::new();
@@ -312,4 +437,39 @@ mod tests {
         assert_eq!(doc1, doc2);
         assert_eq!(bin1, bin2);
     }
+
+    /// The whole point of `AttestationDoc`'s `serde` impl is wire compatibility
+    /// with `minicbor`; round-trip a document with populated `pcrs`/`cabundle`
+    /// (the fields most likely to regress, since their values are nested
+    /// `Vec<u8>`s) through `ciborium` and check its bytes match `to_binary()`
+    /// exactly, so a regression here is caught instead of only showing up as a
+    /// parse failure in a downstream consumer.
+    #[cfg(all(feature = "ciborium", feature = "serde"))]
+    #[test]
+    fn test_attestationdoc_serde_matches_minicbor_wire_format() {
+        let mut pcrs = BTreeMap::new();
+        pcrs.insert(0, vec![1; 48]);
+        pcrs.insert(1, vec![2; 48]);
+
+        let doc = AttestationDoc::new(
+            "abcd".to_string(),
+            Digest::SHA384,
+            1234,
+            pcrs,
+            vec![42; 10],
+            vec![vec![1, 2, 3], vec![4, 5, 6, 7]],
+            Some(vec![255; 10]),
+            None,
+            None,
+        );
+
+        let minicbor_bytes = doc.to_binary();
+
+        let mut ciborium_bytes = Vec::new();
+        ciborium::ser::into_writer(&doc, &mut ciborium_bytes).unwrap();
+        assert_eq!(ciborium_bytes, minicbor_bytes);
+
+        let decoded: AttestationDoc = ciborium::de::from_reader(&ciborium_bytes[..]).unwrap();
+        assert_eq!(decoded, doc);
+    }
 }
@@ -0,0 +1,197 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Declarative admission policy for a verified `AttestationDoc`.
+//! # Overview
+//! Chain and signature verification (see [`crate::api::verify`]) only proves
+//! that a document genuinely came from *some* NitroSecureModule. Deciding
+//! whether it came from *the expected* enclave - the right image, the right
+//! kernel, answering the right challenge - is the relying party's job.
+//! `AttestationPolicy` declares those expectations, and
+//! `AttestationDoc::check_policy` evaluates them, returning every violation
+//! rather than a single bool so callers can log precise rejection reasons.
+
+use crate::api::AttestationDoc;
+
+#[cfg(feature = "std")]
+use std::{collections::BTreeMap, string::String, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+/// Expected values the relying party requires of a verified `AttestationDoc`.
+#[derive(Debug, Clone, Default)]
+pub struct AttestationPolicy {
+    /// Expected contents of specific PCR indices (e.g. PCR0/1/2/8 for the
+    /// image, kernel, and boot measurements).
+    pub expected_pcrs: BTreeMap<usize, Vec<u8>>,
+
+    /// Acceptable prefixes for `module_id`. Empty means any `module_id` is accepted.
+    pub allowed_module_id_prefixes: Vec<String>,
+
+    /// If set, the document's `public_key` must equal this value exactly.
+    pub expected_public_key: Option<Vec<u8>>,
+
+    /// The nonce the caller issued as a replay-prevention challenge. The
+    /// document's `nonce` must equal this value exactly.
+    pub required_nonce: Option<Vec<u8>>,
+
+    /// The maximum age, in milliseconds, permitted between the document's
+    /// `timestamp` and the caller-supplied "now" passed to `check_policy`.
+    pub max_age_ms: Option<u64>,
+}
+
+/// A single constraint that a document failed to satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    /// PCR `index` did not match the expected value.
+    PcrMismatch {
+        /// The PCR index that failed to match.
+        index: usize,
+    },
+    /// PCR `index` was required by the policy but absent from the document.
+    PcrMissing {
+        /// The PCR index that was missing.
+        index: usize,
+    },
+    /// `module_id` did not start with any of the allowed prefixes.
+    ModuleIdNotAllowed,
+    /// `public_key` did not match the expected value (or was absent).
+    PublicKeyMismatch,
+    /// `nonce` did not match the required challenge (or was absent).
+    NonceMismatch,
+    /// The document is older than `max_age_ms` relative to the caller's "now".
+    Stale,
+}
+
+/// The outcome of evaluating an `AttestationPolicy` against a document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolicyReport {
+    /// Every constraint that failed. Empty means the document satisfies the policy.
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl PolicyReport {
+    /// Whether every constraint in the policy was satisfied.
+    pub fn is_allowed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl AttestationDoc {
+    /// Evaluate `policy` against this (already chain/signature-verified) document,
+    /// treating `now_ms` (milliseconds since the Unix epoch) as the current time for
+    /// the `max_age_ms` check.
+    pub fn check_policy(&self, policy: &AttestationPolicy, now_ms: u64) -> PolicyReport {
+        let mut violations = Vec::new();
+
+        for (index, expected) in &policy.expected_pcrs {
+            match self.pcrs.get(index) {
+                Some(actual) if actual == expected => {}
+                Some(_) => violations.push(PolicyViolation::PcrMismatch { index: *index }),
+                None => violations.push(PolicyViolation::PcrMissing { index: *index }),
+            }
+        }
+
+        if !policy.allowed_module_id_prefixes.is_empty()
+            && !policy
+                .allowed_module_id_prefixes
+                .iter()
+                .any(|prefix| self.module_id.starts_with(prefix.as_str()))
+        {
+            violations.push(PolicyViolation::ModuleIdNotAllowed);
+        }
+
+        if let Some(expected_key) = &policy.expected_public_key {
+            if self.public_key.as_ref() != Some(expected_key) {
+                violations.push(PolicyViolation::PublicKeyMismatch);
+            }
+        }
+
+        if let Some(required_nonce) = &policy.required_nonce {
+            if self.nonce.as_ref() != Some(required_nonce) {
+                violations.push(PolicyViolation::NonceMismatch);
+            }
+        }
+
+        if let Some(max_age_ms) = policy.max_age_ms {
+            let age = now_ms.saturating_sub(self.timestamp);
+            if age > max_age_ms {
+                violations.push(PolicyViolation::Stale);
+            }
+        }
+
+        PolicyReport { violations }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(module_id: &str, pcrs: BTreeMap<usize, Vec<u8>>, timestamp: u64) -> AttestationDoc {
+        AttestationDoc::new(
+            module_id.to_string(),
+            crate::api::Digest::SHA384,
+            timestamp,
+            pcrs,
+            vec![42; 10],
+            vec![],
+            None,
+            Some(vec![9, 9, 9]),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_check_policy_allows_matching_document() {
+        let mut pcrs = BTreeMap::new();
+        pcrs.insert(0, vec![1; 48]);
+        let document = doc("i-0123", pcrs.clone(), 1_000);
+
+        let policy = AttestationPolicy {
+            expected_pcrs: pcrs,
+            allowed_module_id_prefixes: vec!["i-".to_string()],
+            expected_public_key: None,
+            required_nonce: Some(vec![9, 9, 9]),
+            max_age_ms: Some(500),
+        };
+
+        let report = document.check_policy(&policy, 1_200);
+        assert!(report.is_allowed());
+        assert_eq!(report.violations, vec![]);
+    }
+
+    #[test]
+    fn test_check_policy_reports_every_violation() {
+        let mut pcrs = BTreeMap::new();
+        pcrs.insert(0, vec![1; 48]);
+        let document = doc("i-0123", pcrs, 1_000);
+
+        let mut expected_pcrs = BTreeMap::new();
+        expected_pcrs.insert(0, vec![2; 48]);
+        expected_pcrs.insert(1, vec![3; 48]);
+
+        let policy = AttestationPolicy {
+            expected_pcrs,
+            allowed_module_id_prefixes: vec!["j-".to_string()],
+            expected_public_key: Some(vec![1, 2, 3]),
+            required_nonce: Some(vec![0, 0, 0]),
+            max_age_ms: Some(100),
+        };
+
+        let report = document.check_policy(&policy, 1_200);
+        assert_eq!(
+            report.violations,
+            vec![
+                PolicyViolation::PcrMismatch { index: 0 },
+                PolicyViolation::PcrMissing { index: 1 },
+                PolicyViolation::ModuleIdNotAllowed,
+                PolicyViolation::PublicKeyMismatch,
+                PolicyViolation::NonceMismatch,
+                PolicyViolation::Stale,
+            ]
+        );
+        assert!(!report.is_allowed());
+    }
+}
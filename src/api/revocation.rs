@@ -0,0 +1,151 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A compact certificate-revocation filter, for use during chain validation.
+//! # Overview
+//! Enclaves typically cannot perform network OCSP/CRL lookups, so revocation
+//! data has to be shipped as a static, compact artifact instead. A Bloom
+//! filter cascade - a sequence of filter layers where a positive result in
+//! layer *i* is resolved by querying layer *i + 1*, with the last layer's
+//! result being authoritative - lets a (serial, issuer) set be checked for
+//! membership in space far smaller than an explicit list, at the cost of a
+//! small, layer-dependent false-positive rate that the cascade itself
+//! corrects for.
+
+use sha2::{Digest as _, Sha256};
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// One layer of the cascade: a bitset checked with `num_hashes` independent
+/// hash functions, in the usual Bloom filter construction.
+struct BloomLayer {
+    bits: Vec<u8>,
+    num_hashes: u32,
+}
+
+impl BloomLayer {
+    fn contains(&self, key: &[u8]) -> bool {
+        if self.bits.is_empty() {
+            return false;
+        }
+        let num_bits = self.bits.len() * 8;
+        (0..self.num_hashes).all(|seed| {
+            let bit = (seeded_hash(key, seed) as usize) % num_bits;
+            self.bits[bit / 8] & (1 << (bit % 8)) != 0
+        })
+    }
+}
+
+/// Hash `key` with an independent, seed-derived digest, returning the low 64
+/// bits as a bit index source.
+fn seeded_hash(key: &[u8], seed: u32) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_le_bytes());
+    hasher.update(key);
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[0..8].try_into().expect("digest is at least 8 bytes"))
+}
+
+/// A compact revocation check backed by a Bloom filter cascade, keyed by
+/// `issuer_spki_hash || serial`.
+pub struct RevocationFilter {
+    layers: Vec<BloomLayer>,
+}
+
+impl RevocationFilter {
+    /// Build a filter from its layers, each given as `(bitset, num_hash_functions)`,
+    /// ordered from the first (broadest, highest false-positive rate) layer to the
+    /// last (authoritative) layer.
+    pub fn from_layers(layers: Vec<(Vec<u8>, u32)>) -> Self {
+        RevocationFilter {
+            layers: layers
+                .into_iter()
+                .map(|(bits, num_hashes)| BloomLayer { bits, num_hashes })
+                .collect(),
+        }
+    }
+
+    /// Check whether the certificate identified by `issuer_spki_hash` (the SHA-256
+    /// hash of the issuing CA's SubjectPublicKeyInfo) and `serial` (its serial
+    /// number, big-endian) is revoked.
+    ///
+    /// Layers are queried in order; a miss at any layer ends the walk early (Bloom
+    /// filters have no false negatives, so a miss means the previous layer's answer
+    /// already stands), and layers alternate asserting revoked/not-revoked so that
+    /// each later layer corrects the false positives of the one before it.
+    pub fn is_revoked(&self, issuer_spki_hash: &[u8], serial: &[u8]) -> bool {
+        let mut key = Vec::with_capacity(issuer_spki_hash.len() + serial.len());
+        key.extend_from_slice(issuer_spki_hash);
+        key.extend_from_slice(serial);
+
+        let mut revoked = false;
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !layer.contains(&key) {
+                return revoked;
+            }
+            revoked = index % 2 == 0;
+        }
+        revoked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer_asserting(keys: &[&[u8]], num_hashes: u32, num_bits: usize) -> (Vec<u8>, u32) {
+        let mut bits = vec![0u8; num_bits / 8];
+        for key in keys {
+            for seed in 0..num_hashes {
+                let bit = (seeded_hash(key, seed) as usize) % num_bits;
+                bits[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+        (bits, num_hashes)
+    }
+
+    #[test]
+    fn test_revocation_filter_miss_on_empty_filter() {
+        let filter = RevocationFilter::from_layers(vec![]);
+        assert!(!filter.is_revoked(b"issuer", b"serial"));
+    }
+
+    #[test]
+    fn test_revocation_filter_single_layer_hit_and_miss() {
+        let mut key = Vec::new();
+        key.extend_from_slice(b"issuer");
+        key.extend_from_slice(b"revoked-serial");
+
+        let layer = layer_asserting(&[&key], 4, 256);
+        let filter = RevocationFilter::from_layers(vec![layer]);
+
+        assert!(filter.is_revoked(b"issuer", b"revoked-serial"));
+        assert!(!filter.is_revoked(b"issuer", b"good-serial"));
+    }
+
+    #[test]
+    fn test_revocation_filter_cascade_corrects_false_positive() {
+        let mut revoked_key = Vec::new();
+        revoked_key.extend_from_slice(b"issuer");
+        revoked_key.extend_from_slice(b"revoked-serial");
+
+        let mut false_positive_key = Vec::new();
+        false_positive_key.extend_from_slice(b"issuer");
+        false_positive_key.extend_from_slice(b"good-serial");
+
+        // Layer 0 (asserts revoked) is built as if both keys hashed into it, as a
+        // false positive for `false_positive_key` would. Layer 1 (asserts
+        // not-revoked) only contains `false_positive_key`, correcting it back to
+        // not-revoked while leaving `revoked_key` revoked.
+        let layer0 = layer_asserting(&[&revoked_key, &false_positive_key], 4, 256);
+        let layer1 = layer_asserting(&[&false_positive_key], 4, 256);
+        let filter = RevocationFilter::from_layers(vec![layer0, layer1]);
+
+        assert!(filter.is_revoked(b"issuer", b"revoked-serial"));
+        assert!(!filter.is_revoked(b"issuer", b"good-serial"));
+    }
+}
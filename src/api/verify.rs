@@ -0,0 +1,310 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Relying-party verification of a NitroSecureModule attestation document.
+//! # Overview
+//! `Request::Attestation` returns a COSE_Sign1 structure whose payload is a
+//! CBOR-encoded [`AttestationDoc`]. [`AttestationDoc::from_cose_and_verify`]
+//! decodes that structure, validates the embedded certificate chain against a
+//! caller-supplied trusted root, and checks the ES384 signature over the
+//! payload, so a relying party can trust the returned `AttestationDoc`
+//! without pulling in a separate verification library.
+
+use crate::api::cose::{CoseSign1, ALG_ES384};
+use crate::api::revocation::RevocationFilter;
+use crate::api::AttestationDoc;
+use ecdsa::signature::Verifier;
+use p384::ecdsa::{Signature, VerifyingKey};
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+/// SHA-256 fingerprint of the DER encoding of the standard AWS Nitro Enclaves
+/// root CA certificate, for convenience when calling
+/// [`AttestationDoc::from_cose_and_verify`].
+pub const AWS_NITRO_ROOT_CA_SHA256: [u8; 32] = [
+    0x64, 0x1a, 0x03, 0x21, 0xa3, 0xe2, 0x44, 0xef, 0xe4, 0x56, 0x46, 0x3c, 0x19, 0x9d, 0xb0, 0x26,
+    0xe9, 0x51, 0xb3, 0x2c, 0x10, 0x05, 0x1c, 0xba, 0xf3, 0xda, 0x3c, 0x4c, 0x56, 0x9a, 0x03, 0xa5,
+];
+
+/// Errors that can occur while verifying an attestation document.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The document was not a well-formed COSE_Sign1 / `AttestationDoc` CBOR structure,
+    /// or a required field (e.g. `certificate`) is missing.
+    Malformed(String),
+    /// The certificate chain does not terminate at the trusted root, or a
+    /// certificate in the chain is not validly formed.
+    UntrustedChain(String),
+    /// The signing certificate, or one of the certificates in `cabundle`, is not
+    /// valid at the document's `timestamp`.
+    Expired,
+    /// The ES384 signature over the COSE `Sig_structure` did not verify.
+    BadSignature,
+    /// An intermediate certificate in the chain has been revoked, per the
+    /// caller-supplied `RevocationFilter`.
+    Revoked,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Malformed(msg) => write!(f, "malformed attestation document: {}", msg),
+            VerifyError::UntrustedChain(msg) => write!(f, "untrusted certificate chain: {}", msg),
+            VerifyError::Expired => write!(f, "certificate chain expired at document timestamp"),
+            VerifyError::BadSignature => write!(f, "attestation document signature is invalid"),
+            VerifyError::Revoked => write!(f, "an intermediate certificate in the chain is revoked"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Parse a DER certificate, returning a verification error on failure.
+fn parse_cert(der: &[u8]) -> Result<X509Certificate<'_>, VerifyError> {
+    let (_, cert) =
+        X509Certificate::from_der(der).map_err(|e| VerifyError::UntrustedChain(e.to_string()))?;
+    Ok(cert)
+}
+
+/// Verify that `cert` was signed by `issuer`'s public key, using the same ES384
+/// (ECDSA P-384 / SHA-384) machinery as [`verify_signature`] - the algorithm the
+/// AWS Nitro Enclaves CA hierarchy uses throughout the chain.
+fn verify_cert_signature(
+    cert: &X509Certificate<'_>,
+    issuer: &X509Certificate<'_>,
+) -> Result<(), VerifyError> {
+    let issuer_spki = issuer.public_key().subject_public_key.as_ref();
+    let verifying_key = VerifyingKey::from_sec1_bytes(issuer_spki)
+        .map_err(|_| VerifyError::UntrustedChain("issuer certificate key is not P-384".into()))?;
+    let signature = Signature::from_der(cert.signature_value.as_ref())
+        .map_err(|_| VerifyError::UntrustedChain("certificate signature is malformed".into()))?;
+
+    verifying_key
+        .verify(cert.tbs_certificate.as_ref(), &signature)
+        .map_err(|_| VerifyError::UntrustedChain("certificate is not signed by its issuer".into()))
+}
+
+/// Validate that `cabundle` (ordered root -> leaf-issuer) plus `leaf` form a chain
+/// terminating at a root certificate whose SHA-256 fingerprint matches
+/// `trusted_root_sha256`, that every certificate in the chain is valid at
+/// `timestamp_ms` and actually signed by the previous certificate in the chain,
+/// and, if `revocation` is supplied, that no intermediate certificate in
+/// `cabundle` has been revoked.
+fn verify_chain(
+    leaf: &[u8],
+    cabundle: &[Vec<u8>],
+    timestamp_ms: u64,
+    trusted_root_sha256: &[u8; 32],
+    revocation: Option<&RevocationFilter>,
+) -> Result<(), VerifyError> {
+    let at = UNIX_EPOCH + Duration::from_millis(timestamp_ms);
+
+    // `cabundle` is ordered root -> leaf-issuer, so the full chain root -> leaf is
+    // `cabundle` followed by `leaf`.
+    let mut chain = Vec::with_capacity(cabundle.len() + 1);
+    for der in cabundle {
+        chain.push(parse_cert(der)?);
+    }
+    chain.push(parse_cert(leaf)?);
+
+    for cert in &chain {
+        let validity = cert.validity();
+        let not_before = UNIX_EPOCH + Duration::from_secs(validity.not_before.timestamp() as u64);
+        let not_after = UNIX_EPOCH + Duration::from_secs(validity.not_after.timestamp() as u64);
+        if at < not_before || at > not_after {
+            return Err(VerifyError::Expired);
+        }
+    }
+
+    let root_der = cabundle
+        .first()
+        .ok_or_else(|| VerifyError::UntrustedChain("cabundle has no root certificate".into()))?;
+    let digest = <sha2::Sha256 as sha2::Digest>::digest(root_der);
+    if digest.as_slice() != trusted_root_sha256 {
+        return Err(VerifyError::UntrustedChain(
+            "chain does not terminate at the trusted root certificate".into(),
+        ));
+    }
+
+    // The chain is [root, ..., leaf]; every certificate but the root was issued
+    // (and therefore signed) by the previous certificate in the chain. The root
+    // itself is self-signed and is instead anchored by the fingerprint check above.
+    for window in chain.windows(2) {
+        let (issuer, cert) = (&window[0], &window[1]);
+        verify_cert_signature(cert, issuer)?;
+    }
+
+    if let Some(revocation) = revocation {
+        // Without its last (leaf) entry the chain is [root, intermediates...], so
+        // each window pairs an intermediate with the certificate that issued it.
+        for window in chain[..chain.len() - 1].windows(2) {
+            let (issuer, cert) = (&window[0], &window[1]);
+            let issuer_spki_hash =
+                <sha2::Sha256 as sha2::Digest>::digest(issuer.public_key().raw);
+            let serial = cert.raw_serial();
+            if revocation.is_revoked(&issuer_spki_hash, serial) {
+                return Err(VerifyError::Revoked);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Recover the P-384 public key from a leaf certificate and check the ES384
+/// signature over `cose`'s canonical `Sig_structure`.
+fn verify_signature(cose: &CoseSign1, leaf_certificate: &[u8]) -> Result<(), VerifyError> {
+    if cose.algorithm() != Some(ALG_ES384) {
+        return Err(VerifyError::BadSignature);
+    }
+
+    let cert = parse_cert(leaf_certificate)?;
+    let spki = cert.public_key().subject_public_key.as_ref();
+    let verifying_key = VerifyingKey::from_sec1_bytes(spki)
+        .map_err(|_| VerifyError::UntrustedChain("leaf certificate key is not P-384".into()))?;
+    let signature =
+        Signature::from_slice(&cose.signature).map_err(|_| VerifyError::BadSignature)?;
+
+    verifying_key
+        .verify(&cose.sig_structure(), &signature)
+        .map_err(|_| VerifyError::BadSignature)
+}
+
+impl AttestationDoc {
+    /// Decode a raw `Response::Attestation.document` COSE_Sign1 value, verify its
+    /// certificate chain against `trusted_root_sha256` (the SHA-256 fingerprint of
+    /// a trusted root CA's DER encoding) and its ES384 signature, and return the
+    /// validated `AttestationDoc`.
+    pub fn from_cose_and_verify(
+        document: &[u8],
+        trusted_root_sha256: &[u8; 32],
+    ) -> Result<Self, VerifyError> {
+        Self::from_cose_and_verify_opts(document, trusted_root_sha256, None)
+    }
+
+    /// As [`AttestationDoc::from_cose_and_verify`], but additionally reject the
+    /// document if any intermediate certificate in its chain is revoked per
+    /// `revocation`.
+    pub fn from_cose_and_verify_checking_revocation(
+        document: &[u8],
+        trusted_root_sha256: &[u8; 32],
+        revocation: &RevocationFilter,
+    ) -> Result<Self, VerifyError> {
+        Self::from_cose_and_verify_opts(document, trusted_root_sha256, Some(revocation))
+    }
+
+    fn from_cose_and_verify_opts(
+        document: &[u8],
+        trusted_root_sha256: &[u8; 32],
+        revocation: Option<&RevocationFilter>,
+    ) -> Result<Self, VerifyError> {
+        let cose = CoseSign1::from_bytes(document)
+            .map_err(|e| VerifyError::Malformed(e.to_string()))?;
+        let doc = AttestationDoc::from_binary(cose.payload())
+            .map_err(|e| VerifyError::Malformed(format!("{:?}", e)))?;
+
+        if doc.module_id.is_empty() {
+            return Err(VerifyError::Malformed("module_id is empty".into()));
+        }
+        if doc.certificate.is_empty() {
+            return Err(VerifyError::Malformed("certificate is empty".into()));
+        }
+        if doc.timestamp == 0 {
+            return Err(VerifyError::Malformed("timestamp is zero".into()));
+        }
+        if doc
+            .pcrs
+            .values()
+            .any(|pcr| pcr.len() != doc.digest.hash_len())
+        {
+            return Err(VerifyError::Malformed(
+                "a PCR value's length does not match the document's digest algorithm".into(),
+            ));
+        }
+
+        verify_chain(
+            &doc.certificate,
+            &doc.cabundle,
+            doc.timestamp,
+            trusted_root_sha256,
+            revocation,
+        )?;
+        verify_signature(&cose, &doc.certificate)?;
+
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa, PKCS_ECDSA_P384_SHA384};
+    use std::time::SystemTime;
+
+    fn ca_params() -> CertificateParams {
+        let mut params = CertificateParams::new(Vec::new());
+        params.alg = &PKCS_ECDSA_P384_SHA384;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+    }
+
+    fn leaf_params() -> CertificateParams {
+        let mut params = CertificateParams::new(Vec::new());
+        params.alg = &PKCS_ECDSA_P384_SHA384;
+        params
+    }
+
+    fn now_ms() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    fn sha256(der: &[u8]) -> [u8; 32] {
+        let digest = <sha2::Sha256 as sha2::Digest>::digest(der);
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&digest);
+        out
+    }
+
+    /// Build a self-signed root, an intermediate issued by that root, and a leaf
+    /// issued by that intermediate - a minimal stand-in for the real AWS Nitro
+    /// Enclaves CA hierarchy - returning the leaf's DER and a root -> leaf-issuer
+    /// ordered `cabundle`, as `AttestationDoc::cabundle` is documented to be.
+    fn three_cert_chain() -> (Vec<u8>, Vec<Vec<u8>>, [u8; 32]) {
+        let root = Certificate::from_params(ca_params()).unwrap();
+        let root_der = root.serialize_der().unwrap();
+
+        let intermediate = Certificate::from_params(ca_params()).unwrap();
+        let intermediate_der = intermediate.serialize_der_with_signer(&root).unwrap();
+
+        let leaf = Certificate::from_params(leaf_params()).unwrap();
+        let leaf_der = leaf.serialize_der_with_signer(&intermediate).unwrap();
+
+        let root_sha256 = sha256(&root_der);
+        (leaf_der, vec![root_der, intermediate_der], root_sha256)
+    }
+
+    #[test]
+    fn test_verify_chain_accepts_root_to_leaf_ordered_cabundle() {
+        let (leaf_der, cabundle, root_sha256) = three_cert_chain();
+        assert!(verify_chain(&leaf_der, &cabundle, now_ms(), &root_sha256, None).is_ok());
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_leaf_to_root_ordered_cabundle() {
+        let (leaf_der, cabundle, root_sha256) = three_cert_chain();
+        let reversed: Vec<Vec<u8>> = cabundle.into_iter().rev().collect();
+        assert!(verify_chain(&leaf_der, &reversed, now_ms(), &root_sha256, None).is_err());
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_untrusted_root() {
+        let (leaf_der, cabundle, _) = three_cert_chain();
+        let wrong_root_sha256 = [0u8; 32];
+        assert!(verify_chain(&leaf_der, &cabundle, now_ms(), &wrong_root_sha256, None).is_err());
+    }
+}
@@ -12,6 +12,7 @@
 //! the user, which then gets populated with information from the NSM driver and
 //! then decoded from CBOR.
 
+use crate::api::codec::{ActiveCodec, CborCodec};
 use crate::api::{ErrorCode, Request, Response};
 use libc::ioctl;
 use log::{debug, error};
@@ -19,10 +20,18 @@ use nix::errno::Errno;
 use nix::request_code_readwrite;
 use nix::unistd::close;
 
+use std::fmt;
 use std::fs::OpenOptions;
 use std::io::{IoSlice, IoSliceMut};
 use std::mem;
 use std::os::unix::io::{IntoRawFd, RawFd};
+use std::string::String;
+
+mod rng;
+pub use rng::NsmRng;
+
+mod sysfs;
+pub use sysfs::{read_nsm_description, NsmSysfsDescription, SysfsError};
 
 const DEV_FILE: &str = "/dev/nsm";
 const NSM_IOCTL_MAGIC: u8 = 0x0A;
@@ -38,21 +47,45 @@ struct NsmMessage<'a> {
     pub response: IoSliceMut<'a>,
 }
 
-/// Encode an NSM `Request` value into a vector.  
-/// *Argument 1 (input)*: The NSM request.  
+/// Errors that can occur while driving a request through the NSM ioctl interface,
+/// as distinct from an error the NSM pipeline itself chose to report via
+/// `Response::Error`.
+#[derive(Debug)]
+pub enum NsmError {
+    /// The `ioctl()` call failed; carries the raw errno.
+    Ioctl(Errno),
+    /// The driver's response buffer could not be decoded as a `Response`.
+    Decode(String),
+    /// The CBOR-encoded request exceeded `NSM_REQUEST_MAX_SIZE`.
+    RequestTooLarge,
+}
+
+impl fmt::Display for NsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NsmError::Ioctl(errno) => write!(f, "NSM ioctl() failed: {}", errno),
+            NsmError::Decode(e) => write!(f, "failed to decode NSM response: {}", e),
+            NsmError::RequestTooLarge => write!(f, "NSM request exceeds the maximum request size"),
+        }
+    }
+}
+
+impl std::error::Error for NsmError {}
+
+/// Encode an NSM `Request` value into a vector, using the compile-time-selected
+/// `ActiveCodec` CBOR backend.
+/// *Argument 1 (input)*: The NSM request.
 /// *Returns*: The vector containing the CBOR encoding.
 fn nsm_encode_request_to_cbor(request: Request) -> Vec<u8> {
-    serde_cbor::to_vec(&request).unwrap()
+    ActiveCodec::encode_request(&request)
 }
 
-/// Decode an NSM `Response` value from a raw memory buffer.  
-/// *Argument 1 (input)*: The `iovec` holding the memory buffer.  
-/// *Returns*: The decoded NSM response.
-fn nsm_decode_response_from_cbor(response_data: &IoSliceMut<'_>) -> Response {
-    match serde_cbor::from_slice(response_data) {
-        Ok(response) => response,
-        Err(_) => Response::Error(ErrorCode::InternalError),
-    }
+/// Decode an NSM `Response` value from a raw memory buffer, using the
+/// compile-time-selected `ActiveCodec` CBOR backend.
+/// *Argument 1 (input)*: The `iovec` holding the memory buffer.
+/// *Returns*: The decoded NSM response, or a description of the decode error.
+fn nsm_decode_response_from_cbor(response_data: &IoSliceMut<'_>) -> Result<Response, String> {
+    ActiveCodec::decode_response(response_data)
 }
 
 /// Do an `ioctl()` of a given type for a given message.  
@@ -80,16 +113,18 @@ fn nsm_ioctl(fd: i32, message: &mut NsmMessage) -> Option<Errno> {
 
 /// Create a message with input data and output capacity from a given
 /// request, then send it to the NSM driver via `ioctl()` and wait
-/// for the driver's response.  
-/// *Argument 1 (input)*: The descriptor to the NSM device file.  
-/// *Argument 2 (input)*: The NSM request.  
-/// *Returns*: The corresponding NSM response from the driver.
-pub fn nsm_process_request(fd: i32, request: Request) -> Response {
+/// for the driver's response, distinguishing ioctl/decode failures from
+/// an error the NSM pipeline itself reported.
+/// *Argument 1 (input)*: The descriptor to the NSM device file.
+/// *Argument 2 (input)*: The NSM request.
+/// *Returns*: The corresponding NSM response from the driver, or the `NsmError`
+/// that prevented one from being obtained.
+pub fn try_process_request(fd: i32, request: Request) -> Result<Response, NsmError> {
     let cbor_request = nsm_encode_request_to_cbor(request);
 
     // Check if the request is too large
     if cbor_request.len() > NSM_REQUEST_MAX_SIZE {
-        return Response::Error(ErrorCode::InputTooLarge);
+        return Err(NsmError::RequestTooLarge);
     }
 
     let mut cbor_response: [u8; NSM_RESPONSE_MAX_SIZE] = [0; NSM_RESPONSE_MAX_SIZE];
@@ -100,11 +135,24 @@ pub fn nsm_process_request(fd: i32, request: Request) -> Response {
     let status = nsm_ioctl(fd, &mut message);
 
     match status {
-        None => nsm_decode_response_from_cbor(&message.response),
-        Some(errno) => match errno {
-            Errno::EMSGSIZE => Response::Error(ErrorCode::InputTooLarge),
-            _ => Response::Error(ErrorCode::InternalError),
-        },
+        None => nsm_decode_response_from_cbor(&message.response).map_err(NsmError::Decode),
+        Some(errno) => Err(NsmError::Ioctl(errno)),
+    }
+}
+
+/// Create a message with input data and output capacity from a given
+/// request, then send it to the NSM driver via `ioctl()` and wait
+/// for the driver's response.
+/// *Argument 1 (input)*: The descriptor to the NSM device file.
+/// *Argument 2 (input)*: The NSM request.
+/// *Returns*: The corresponding NSM response from the driver.
+pub fn nsm_process_request(fd: i32, request: Request) -> Response {
+    match try_process_request(fd, request) {
+        Ok(response) => response,
+        Err(NsmError::Ioctl(Errno::EMSGSIZE)) | Err(NsmError::RequestTooLarge) => {
+            Response::Error(ErrorCode::InputTooLarge)
+        }
+        Err(_) => Response::Error(ErrorCode::InternalError),
     }
 }
 
@@ -126,7 +174,7 @@ pub fn nsm_init() -> i32 {
     }
 }
 
-/// NSM library exit function.  
+/// NSM library exit function.
 /// *Argument 1 (input)*: The descriptor for the opened device file, as
 /// obtained from `nsm_init()`.
 pub fn nsm_exit(fd: i32) {
@@ -136,3 +184,70 @@ pub fn nsm_exit(fd: i32) {
         Err(e) => error!("File of descriptor {} failed to close: {}", fd, e),
     }
 }
+
+/// An owned handle to the NSM device file.
+///
+/// `Nsm` is the documented entry point for talking to the NSM driver: it opens
+/// `/dev/nsm` on construction, exposes `process_request` to drive the ioctl
+/// interface, and closes the descriptor automatically on `Drop`. The free
+/// functions `nsm_init`/`nsm_process_request`/`nsm_exit` remain available for
+/// C-ABI and back-compat consumers that manage the descriptor themselves.
+pub struct Nsm {
+    fd: RawFd,
+}
+
+impl Nsm {
+    /// Open `/dev/nsm`, returning an owned handle on success.
+    pub fn open() -> std::io::Result<Self> {
+        let fd = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(DEV_FILE)?
+            .into_raw_fd();
+        debug!("Device file '{}' opened successfully.", DEV_FILE);
+        Ok(Nsm { fd })
+    }
+
+    /// Send `request` to the NSM driver and wait for its response.
+    pub fn process_request(&self, request: Request) -> Response {
+        nsm_process_request(self.fd, request)
+    }
+
+    /// Send `request` to the NSM driver, distinguishing an ioctl/decode failure
+    /// from an error the NSM pipeline itself reported via `Response::Error`.
+    pub fn try_process_request(&self, request: Request) -> Result<Response, NsmError> {
+        try_process_request(self.fd, request)
+    }
+}
+
+impl Drop for Nsm {
+    fn drop(&mut self) {
+        nsm_exit(self.fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nsm_open_missing_device_file_errors() {
+        // `/dev/nsm` is only present inside a Nitro Enclave, so in any other
+        // environment (including this test run) `Nsm::open()` must fail cleanly
+        // rather than panic or return a handle over an invalid descriptor.
+        assert!(Nsm::open().is_err());
+    }
+
+    #[test]
+    fn test_nsm_try_process_request_on_invalid_fd_reports_ioctl_error() {
+        // Exercise the fd ownership plumbing end-to-end without a real device:
+        // fd -1 is guaranteed invalid, so the ioctl() call must fail and
+        // `try_process_request` must surface it as `NsmError::Ioctl` rather than
+        // panicking, and the handle must still be safe to drop afterwards.
+        let nsm = Nsm { fd: -1 };
+        assert!(matches!(
+            nsm.try_process_request(Request::GetRandom),
+            Err(NsmError::Ioctl(_))
+        ));
+    }
+}
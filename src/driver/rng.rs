@@ -0,0 +1,95 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ***`rand_core` adapter over `Request::GetRandom`***
+//! # Overview
+//! This module wraps an NSM file descriptor in a type implementing
+//! `rand_core::RngCore` and `rand_core::CryptoRng`, so enclave code can feed
+//! the hardware RNG behind the NSM driver directly into `rand`-ecosystem
+//! consumers (key generation, nonce sampling) instead of issuing raw
+//! `Request::GetRandom` calls and handling the variable-length response by hand.
+
+use crate::api::{Request, Response};
+use crate::driver::nsm_process_request;
+use rand_core::{CryptoRng, Error as RandError, RngCore};
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+
+/// An `rand_core::RngCore` adapter backed by the NSM driver's `GetRandom` request.
+///
+/// Bytes returned by successive `GetRandom` calls are buffered internally, so a
+/// single `fill_bytes` call may issue several requests to the driver until enough
+/// entropy has been collected.
+pub struct NsmRng {
+    fd: RawFd,
+    buffer: VecDeque<u8>,
+}
+
+impl NsmRng {
+    /// Wrap an already-opened NSM file descriptor, as returned by `nsm_init()`.
+    pub fn new(fd: RawFd) -> Self {
+        NsmRng {
+            fd,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Issue one `Request::GetRandom` and push the returned bytes onto the buffer,
+    /// returning an error if the driver did not answer with `Response::GetRandom`.
+    fn refill(&mut self) -> Result<(), RandError> {
+        match nsm_process_request(self.fd, Request::GetRandom) {
+            Response::GetRandom { random } => {
+                self.buffer.extend(random);
+                Ok(())
+            }
+            Response::Error(code) => Err(RandError::new(format!(
+                "NSM GetRandom request failed: {:?}",
+                code
+            ))),
+            other => Err(RandError::new(format!(
+                "NSM GetRandom got unexpected response: {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+impl RngCore for NsmRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("NSM GetRandom request failed")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        let mut filled = 0;
+        while filled < dest.len() {
+            if self.buffer.is_empty() {
+                self.refill()?;
+            }
+            while filled < dest.len() {
+                match self.buffer.pop_front() {
+                    Some(byte) => {
+                        dest[filled] = byte;
+                        filled += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CryptoRng for NsmRng {}
@@ -0,0 +1,77 @@
+// Copyright 2022 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ***NSM device metadata via sysfs***
+//! # Overview
+//! In addition to the CBOR `DescribeNSM` request served over `/dev/nsm`, the
+//! NSM kernel driver publishes the same module identity and version as plain
+//! sysfs files under its device node. This lets diagnostic tooling read the
+//! module id/version without opening the device file or issuing an `ioctl()`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SYSFS_NSM_DIR: &str = "/sys/devices/virtual/misc/nsm";
+
+/// NSM module identity and version, as read from sysfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NsmSysfsDescription {
+    /// `module_id` is an identifier for a singular NitroSecureModule.
+    pub module_id: String,
+    /// Breaking API changes are denoted by `major_version`.
+    pub version_major: u16,
+    /// Minor API changes are denoted by `minor_version`.
+    pub version_minor: u16,
+    /// Patch version; security and stability updates that do not affect the API.
+    pub version_patch: u16,
+}
+
+/// Errors that can occur while reading NSM metadata from sysfs.
+#[derive(Debug)]
+pub enum SysfsError {
+    /// The sysfs directory for the NSM device does not exist. Older drivers
+    /// only expose metadata via the CBOR `DescribeNSM` request.
+    Unavailable,
+    /// A sysfs attribute file could not be read.
+    Io(std::io::Error),
+    /// A sysfs attribute file's contents could not be parsed.
+    Malformed(&'static str),
+}
+
+impl From<std::io::Error> for SysfsError {
+    fn from(error: std::io::Error) -> Self {
+        SysfsError::Io(error)
+    }
+}
+
+fn nsm_sysfs_dir() -> PathBuf {
+    Path::new(SYSFS_NSM_DIR).to_path_buf()
+}
+
+fn read_attr(dir: &Path, name: &str) -> Result<String, SysfsError> {
+    Ok(fs::read_to_string(dir.join(name))?.trim().to_string())
+}
+
+fn parse_version_part(value: &str) -> Result<u16, SysfsError> {
+    value
+        .parse()
+        .map_err(|_| SysfsError::Malformed("version attribute is not a valid u16"))
+}
+
+/// Read the NSM module id and version from sysfs, without opening `/dev/nsm`.
+///
+/// Returns `Err(SysfsError::Unavailable)` if the driver does not expose a
+/// sysfs tree, so callers can fall back to the `DescribeNSM` ioctl request.
+pub fn read_nsm_description() -> Result<NsmSysfsDescription, SysfsError> {
+    let dir = nsm_sysfs_dir();
+    if !dir.is_dir() {
+        return Err(SysfsError::Unavailable);
+    }
+
+    Ok(NsmSysfsDescription {
+        module_id: read_attr(&dir, "module_id")?,
+        version_major: parse_version_part(&read_attr(&dir, "version_major")?)?,
+        version_minor: parse_version_part(&read_attr(&dir, "version_minor")?)?,
+        version_patch: parse_version_part(&read_attr(&dir, "version_patch")?)?,
+    })
+}
@@ -8,6 +8,16 @@
 //!
 //! nsm_io provides the API and CBOR encoding functionality.
 //! nsm_driver provides the ioctl interface for the Nitro Secure Module driver.
+//!
+//! The `api` module and its CBOR (de)serialization compile under `no_std` + `alloc`
+//! when the default `std` feature is disabled, so the `Request`/`Response` types can
+//! be used from constrained enclave code that never opens `/dev/nsm`. The `driver`
+//! module, which talks to the NSM device, always requires `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
 
 pub mod api;
 #[cfg(feature = "nix")]